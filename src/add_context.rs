@@ -1,7 +1,9 @@
 //! Hyper service that adds a context to an incoming request and passes it on
 //! to a wrapped service.
 
-use crate::{ContextualPayload, Push, XSpanId};
+use crate::auth::api_key_from_header_or_query;
+use crate::{AuthData, Authorization, ContextualPayload, Push, XSpanId};
+use headers::HeaderMapExt;
 use std::marker::PhantomData;
 use std::task::{Context, Poll};
 
@@ -9,11 +11,11 @@ use std::task::{Context, Poll};
 /// stack of hyper services. Adds a context to a plain `hyper::Request` that can be
 /// used by subsequent layers in the stack.
 #[derive(Debug)]
-pub struct AddContextMakeService<C> {
-    phantom: PhantomData<C>
+pub struct AddContextMakeService<C, B = hyper::Body> {
+    phantom: PhantomData<(C, B)>
 }
 
-impl<C> AddContextMakeService<C> {
+impl<C, B> AddContextMakeService<C, B> {
     /// Create a new AddContextMakeService struct wrapping a value
     pub fn new() -> Self {
         AddContextMakeService {
@@ -22,8 +24,8 @@ impl<C> AddContextMakeService<C> {
     }
 }
 
-impl<T, C> hyper::service::Service<T> for AddContextMakeService<C> {
-    type Response = AddContextService<T, C>;
+impl<T, C, B> hyper::service::Service<T> for AddContextMakeService<C, B> {
+    type Response = AddContextService<T, C, B>;
     type Error = std::io::Error;
     type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
 
@@ -41,13 +43,17 @@ impl<T, C> hyper::service::Service<T> for AddContextMakeService<C> {
 /// used by subsequent layers in the stack. The `AddContextService` struct should
 /// not usually be used directly - when constructing a hyper stack use
 /// `AddContextMakeService`, which will create `AddContextService` instances as needed.
+///
+/// Generic over the request body type `B` (defaulting to `hyper::Body` for
+/// source compatibility), so that services wrapping pre-buffered or otherwise
+/// transformed bodies can still be composed with `AddContextService`.
 #[derive(Debug)]
-pub struct AddContextService<T, C> {
+pub struct AddContextService<T, C, B = hyper::Body> {
     inner: T,
-    marker: PhantomData<C>,
+    marker: PhantomData<(C, B)>,
 }
 
-impl<T, C> AddContextService<T, C> {
+impl<T, C, B> AddContextService<T, C, B> {
     /// Create a new AddContextService struct wrapping a value
     pub fn new(inner: T) -> Self {
         AddContextService {
@@ -57,11 +63,12 @@ impl<T, C> AddContextService<T, C> {
     }
 }
 
-impl<T, C> hyper::service::Service<hyper::Request<hyper::Body>> for AddContextService<T, C>
+impl<T, C, B> hyper::service::Service<hyper::Request<B>> for AddContextService<T, C, B>
     where
+        B: http_body::Body + Send + 'static,
         C: Default + Push<XSpanId> + Send + Sync + 'static,
         C::Result: Send + Sync + 'static,
-        T: hyper::service::Service<ContextualPayload<C::Result>>,
+        T: hyper::service::Service<ContextualPayload<C::Result, B>>,
 {
     type Response = T::Response;
     type Error = T::Error;
@@ -71,7 +78,7 @@ impl<T, C> hyper::service::Service<hyper::Request<hyper::Body>> for AddContextSe
         Ok(()).into()
     }
 
-    fn call(&mut self, req: hyper::Request<hyper::Body>) -> Self::Future {
+    fn call(&mut self, req: hyper::Request<B>) -> Self::Future {
         let x_span_id = XSpanId::get_or_generate(&req);
         let context = C::default().push(x_span_id);
 
@@ -82,4 +89,138 @@ impl<T, C> hyper::service::Service<hyper::Request<hyper::Body>> for AddContextSe
     }
 }
 
+/// Middleware wrapper service, that should be used as the outermost layer in a
+/// stack of hyper services. Builds the full `XSpanId` -> `Option<AuthData>` ->
+/// `Option<Authorization>` context chain in one step, rather than requiring an
+/// `AddContextMakeService` to be composed with a separate authenticator. The
+/// `MakeAddContext` struct should not usually be used directly - when
+/// constructing a hyper stack use `MakeAddContextMakeService`, which will
+/// create `MakeAddContext` instances as needed.
+#[derive(Debug)]
+pub struct MakeAddContext<T, A, B = hyper::Body> {
+    inner: T,
+    api_key_header: Option<String>,
+    marker: PhantomData<(A, B)>,
+}
+
+impl<T, A, B> MakeAddContext<T, A, B> {
+    /// Create a new MakeAddContext struct wrapping a value
+    pub fn new(inner: T) -> Self {
+        MakeAddContext {
+            inner,
+            api_key_header: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Create a new MakeAddContext struct wrapping a value, additionally
+    /// looking for an API key under the given header (or, failing that,
+    /// query parameter) name.
+    pub fn with_api_key_header<S: Into<String>>(inner: T, api_key_header: S) -> Self {
+        MakeAddContext {
+            inner,
+            api_key_header: Some(api_key_header.into()),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A, B, Ctx1, Ctx2, Ctx3> hyper::service::Service<hyper::Request<B>> for MakeAddContext<T, A, B>
+    where
+        B: http_body::Body + Send + 'static,
+        A: Default + Push<XSpanId, Result = Ctx1>,
+        Ctx1: Push<Option<AuthData>, Result = Ctx2>,
+        Ctx2: Push<Option<Authorization>, Result = Ctx3>,
+        Ctx3: Send + Sync + 'static,
+        T: hyper::service::Service<ContextualPayload<Ctx3, B>>,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: hyper::Request<B>) -> Self::Future {
+        let x_span_id = XSpanId::get_or_generate(&req);
+
+        let headers = req.headers();
+        let query = req.uri().query().unwrap_or("");
+
+        let auth_data = headers
+            .typed_get::<headers::Authorization<headers::authorization::Basic>>()
+            .map(AuthData::Basic)
+            .or_else(|| {
+                headers
+                    .typed_get::<headers::Authorization<headers::authorization::Bearer>>()
+                    .map(AuthData::Bearer)
+            })
+            .or_else(|| {
+                self.api_key_header
+                    .as_ref()
+                    .and_then(|header| api_key_from_header_or_query(headers, query, header))
+                    .map(AuthData::ApiKey)
+            });
+
+        let context = A::default()
+            .push(x_span_id)
+            .push(auth_data)
+            .push(None::<Authorization>);
+
+        self.inner.call(ContextualPayload {
+            inner: req,
+            context: context,
+        })
+    }
+}
+
+/// MakeService that creates `MakeAddContext` instances for each connection,
+/// such that a generated server only needs to compose a single layer to get
+/// the full `XSpanId` -> `Option<AuthData>` -> `Option<Authorization>` context
+/// stack, rooted at `A::default()` (for example an `EmptyContext`).
+#[derive(Debug)]
+pub struct MakeAddContextMakeService<A, B = hyper::Body> {
+    api_key_header: Option<String>,
+    phantom: PhantomData<(A, B)>,
+}
+
+impl<A, B> MakeAddContextMakeService<A, B> {
+    /// Create a new MakeAddContextMakeService struct
+    pub fn new() -> Self {
+        MakeAddContextMakeService {
+            api_key_header: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create a new MakeAddContextMakeService struct that looks for API keys
+    /// under the given header (or query parameter) name.
+    pub fn with_api_key_header<S: Into<String>>(api_key_header: S) -> Self {
+        MakeAddContextMakeService {
+            api_key_header: Some(api_key_header.into()),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, A, B> hyper::service::Service<T> for MakeAddContextMakeService<A, B> {
+    type Response = MakeAddContext<T, A, B>;
+    type Error = std::io::Error;
+    type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, inner: T) -> Self::Future {
+        let service = match &self.api_key_header {
+            Some(header) => MakeAddContext::with_api_key_header(inner, header.clone()),
+            None => MakeAddContext::new(inner),
+        };
+
+        futures::future::ok(service)
+    }
+}
+
 
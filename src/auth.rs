@@ -1,8 +1,11 @@
 //! Authentication and authorization data structures
 
-use crate::{Push, ContextualPayload};
+use crate::{Has, Push, ContextualPayload};
+use headers::HeaderMapExt;
 use hyper::HeaderMap;
 use std::collections::BTreeSet;
+use std::future::Future;
+use std::pin::Pin;
 use std::string::ToString;
 use std::marker::PhantomData;
 use std::task::{Context, Poll};
@@ -86,15 +89,32 @@ pub fn api_key_from_header(headers: &HeaderMap, header: &str) -> Option<String>
         .map(ToString::to_string)
 }
 
+/// Retrieve an API key from a query string, looking for the given parameter name.
+pub fn api_key_from_query(query: &str, param: &str) -> Option<String> {
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == param)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Retrieve an API key from a header, falling back to the query string if the
+/// header is not present.
+pub fn api_key_from_header_or_query(
+    headers: &HeaderMap,
+    query: &str,
+    param: &str,
+) -> Option<String> {
+    api_key_from_header(headers, param).or_else(|| api_key_from_query(query, param))
+}
+
 /// Dummy Authenticator, that blindly inserts authorization data, allowing all
 /// access to an endpoint with the specified subject.
 #[derive(Debug)]
-pub struct AllowAllAuthenticatorMakeService<C> {
+pub struct AllowAllAuthenticatorMakeService<C, B = hyper::Body> {
     subject: String,
-    phantom: PhantomData<C>,
+    phantom: PhantomData<(C, B)>,
 }
 
-impl<C> AllowAllAuthenticatorMakeService<C> {
+impl<C, B> AllowAllAuthenticatorMakeService<C, B> {
     /// Create a new AddContextMakeService struct wrapping a value
     pub fn new<T: Into<String>>(subject: T) -> Self {
         AllowAllAuthenticatorMakeService {
@@ -104,8 +124,8 @@ impl<C> AllowAllAuthenticatorMakeService<C> {
     }
 }
 
-impl<T, C> hyper::service::Service<T> for AllowAllAuthenticatorMakeService<C> {
-    type Response = AllowAllAuthenticator<T, C>;
+impl<T, C, B> hyper::service::Service<T> for AllowAllAuthenticatorMakeService<C, B> {
+    type Response = AllowAllAuthenticator<T, C, B>;
     type Error = std::io::Error;
     type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
 
@@ -123,14 +143,17 @@ impl<T, C> hyper::service::Service<T> for AllowAllAuthenticatorMakeService<C> {
 /// used by subsequent layers in the stack. The `AddContextService` struct should
 /// not usually be used directly - when constructing a hyper stack use
 /// `AddContextMakeService`, which will create `AddContextService` instances as needed.
+///
+/// Generic over the request body type `B` (defaulting to `hyper::Body`), matching
+/// the body type parameter on `AddContextService`/`DropContextService`.
 #[derive(Debug)]
-pub struct AllowAllAuthenticator<T, C> {
+pub struct AllowAllAuthenticator<T, C, B = hyper::Body> {
     inner: T,
     subject: String,
-    marker: PhantomData<C>,
+    marker: PhantomData<(C, B)>,
 }
 
-impl<T, C> AllowAllAuthenticator<T, C> {
+impl<T, C, B> AllowAllAuthenticator<T, C, B> {
     /// Create a new AddContextService struct wrapping a value
     pub fn new<U: Into<String>>(inner: T, subject: U) -> Self {
         AllowAllAuthenticator {
@@ -141,11 +164,12 @@ impl<T, C> AllowAllAuthenticator<T, C> {
     }
 }
 
-impl<T, C> hyper::service::Service<ContextualPayload<C>> for AllowAllAuthenticator<T, C>
+impl<T, C, B> hyper::service::Service<ContextualPayload<C, B>> for AllowAllAuthenticator<T, C, B>
     where
         C: RcBound,
         C::Result: Send + Sync + 'static,
-        T: hyper::service::Service<ContextualPayload<C::Result>>,
+        B: http_body::Body + Send + 'static,
+        T: hyper::service::Service<ContextualPayload<C::Result, B>>,
 {
     type Response = T::Response;
     type Error = T::Error;
@@ -155,7 +179,7 @@ impl<T, C> hyper::service::Service<ContextualPayload<C>> for AllowAllAuthenticat
         Ok(()).into()
     }
 
-    fn call(&mut self, req: ContextualPayload<C>) -> Self::Future {
+    fn call(&mut self, req: ContextualPayload<C, B>) -> Self::Future {
         let auth = Authorization {
             subject: self.subject.clone(),
             scopes: Scopes::All,
@@ -169,3 +193,264 @@ impl<T, C> hyper::service::Service<ContextualPayload<C>> for AllowAllAuthenticat
         })
     }
 }
+
+/// Middleware that examines an incoming request and populates `Option<AuthData>`
+/// into the context, based on the `Authorization` header (Basic or Bearer) or,
+/// failing that, an API key found under a configurable name, first among the
+/// headers and then in the query string. Unlike `AllowAllAuthenticator`,
+/// this does not grant any `Authorization` - it only surfaces the raw
+/// authentication data for downstream middleware (e.g. a scope-checking
+/// authenticator) to interpret.
+#[derive(Debug)]
+pub struct ExtractAuthDataMakeService<C, B = hyper::Body> {
+    api_key_header: Option<String>,
+    phantom: PhantomData<(C, B)>,
+}
+
+impl<C, B> ExtractAuthDataMakeService<C, B> {
+    /// Create a new ExtractAuthDataMakeService struct wrapping a value, using
+    /// the given header name (if any) to look for an API key when no
+    /// `Authorization` header is present.
+    pub fn new<T: Into<String>>(api_key_header: Option<T>) -> Self {
+        ExtractAuthDataMakeService {
+            api_key_header: api_key_header.map(Into::into),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, C, B> hyper::service::Service<T> for ExtractAuthDataMakeService<C, B> {
+    type Response = ExtractAuthDataService<T, C, B>;
+    type Error = std::io::Error;
+    type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, inner: T) -> Self::Future {
+        futures::future::ok(ExtractAuthDataService::new(inner, self.api_key_header.clone()))
+    }
+}
+
+/// Middleware wrapper service, that should be used as an outer layer in a
+/// stack of hyper services, after `AddContextService` has run. Inspects the
+/// request headers and pushes `Option<AuthData>` onto the context. The
+/// `ExtractAuthDataService` struct should not usually be used directly - when
+/// constructing a hyper stack use `ExtractAuthDataMakeService`, which will
+/// create `ExtractAuthDataService` instances as needed.
+///
+/// Generic over the request body type `B` (defaulting to `hyper::Body`), matching
+/// the body type parameter on `AddContextService`/`DropContextService`.
+#[derive(Debug)]
+pub struct ExtractAuthDataService<T, C, B = hyper::Body> {
+    inner: T,
+    api_key_header: Option<String>,
+    marker: PhantomData<(C, B)>,
+}
+
+impl<T, C, B> ExtractAuthDataService<T, C, B> {
+    /// Create a new ExtractAuthDataService struct wrapping a value
+    pub fn new(inner: T, api_key_header: Option<String>) -> Self {
+        ExtractAuthDataService {
+            inner,
+            api_key_header,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, C, B> hyper::service::Service<ContextualPayload<C, B>> for ExtractAuthDataService<T, C, B>
+    where
+        C: Push<Option<AuthData>> + Send + Sync + 'static,
+        C::Result: Send + Sync + 'static,
+        B: http_body::Body + Send + 'static,
+        T: hyper::service::Service<ContextualPayload<C::Result, B>>,
+{
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = T::Future;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: ContextualPayload<C, B>) -> Self::Future {
+        let headers = req.inner.headers();
+        let query = req.inner.uri().query().unwrap_or("");
+
+        let auth_data = headers
+            .typed_get::<headers::Authorization<headers::authorization::Basic>>()
+            .map(AuthData::Basic)
+            .or_else(|| {
+                headers
+                    .typed_get::<headers::Authorization<headers::authorization::Bearer>>()
+                    .map(AuthData::Bearer)
+            })
+            .or_else(|| {
+                self.api_key_header
+                    .as_ref()
+                    .and_then(|header| api_key_from_header_or_query(headers, query, header))
+                    .map(AuthData::ApiKey)
+            });
+
+        let context = req.context.push(auth_data);
+
+        self.inner.call(ContextualPayload {
+            inner: req.inner,
+            context: context,
+        })
+    }
+}
+
+/// Middleware wrapper service, that should be used to guard endpoints that
+/// require one or more scopes. Reads `Option<Authorization>` from the context,
+/// and responds with `403 Forbidden` instead of calling the inner service if
+/// the required scopes are not all present in `Scopes::Some`. `Scopes::All`
+/// always passes, and a missing `Authorization` is always rejected.
+#[derive(Debug)]
+pub struct ScopeCheckAuthenticatorMakeService<C, B = hyper::Body> {
+    required_scopes: BTreeSet<String>,
+    phantom: PhantomData<(C, B)>,
+}
+
+impl<C, B> ScopeCheckAuthenticatorMakeService<C, B> {
+    /// Create a new ScopeCheckAuthenticatorMakeService struct wrapping a value
+    pub fn new(required_scopes: BTreeSet<String>) -> Self {
+        ScopeCheckAuthenticatorMakeService {
+            required_scopes,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, C, B> hyper::service::Service<T> for ScopeCheckAuthenticatorMakeService<C, B> {
+    type Response = ScopeCheckAuthenticator<T, C, B>;
+    type Error = std::io::Error;
+    type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, inner: T) -> Self::Future {
+        futures::future::ok(ScopeCheckAuthenticator::new(inner, self.required_scopes.clone()))
+    }
+}
+
+/// Middleware wrapper service, that rejects requests which do not carry all of
+/// `required_scopes`. The `ScopeCheckAuthenticator` struct should not usually
+/// be used directly - when constructing a hyper stack use
+/// `ScopeCheckAuthenticatorMakeService`, which will create
+/// `ScopeCheckAuthenticator` instances as needed.
+///
+/// Generic over the request body type `B` (defaulting to `hyper::Body`), matching
+/// the body type parameter on `AddContextService`/`DropContextService`. The
+/// response body remains `hyper::Body`, since the `403 Forbidden` short-circuit
+/// response this middleware may generate is always plain.
+#[derive(Debug)]
+pub struct ScopeCheckAuthenticator<T, C, B = hyper::Body> {
+    inner: T,
+    required_scopes: BTreeSet<String>,
+    marker: PhantomData<(C, B)>,
+}
+
+impl<T, C, B> ScopeCheckAuthenticator<T, C, B> {
+    /// Create a new ScopeCheckAuthenticator struct wrapping a value
+    pub fn new(inner: T, required_scopes: BTreeSet<String>) -> Self {
+        ScopeCheckAuthenticator {
+            inner,
+            required_scopes,
+            marker: PhantomData,
+        }
+    }
+
+    fn is_authorized(&self, authorization: &Option<Authorization>) -> bool {
+        match authorization {
+            Some(Authorization { scopes: Scopes::All, .. }) => true,
+            Some(Authorization { scopes: Scopes::Some(granted), .. }) => {
+                self.required_scopes.is_subset(granted)
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T, C, B> hyper::service::Service<ContextualPayload<C, B>> for ScopeCheckAuthenticator<T, C, B>
+    where
+        C: Has<Option<Authorization>> + Send + Sync + 'static,
+        B: Send + 'static,
+        T: hyper::service::Service<ContextualPayload<C, B>, Response = hyper::Response<hyper::Body>>,
+        T::Future: Send + 'static,
+        T::Error: Send + 'static,
+{
+    type Response = hyper::Response<hyper::Body>;
+    type Error = T::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: ContextualPayload<C, B>) -> Self::Future {
+        if self.is_authorized(Has::<Option<Authorization>>::get(&req.context)) {
+            Box::pin(self.inner.call(req))
+        } else {
+            let response = hyper::Response::builder()
+                .status(hyper::StatusCode::FORBIDDEN)
+                .body(hyper::Body::empty())
+                .expect("Unable to create Forbidden response");
+
+            Box::pin(futures::future::ok(response))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(scopes: &[&str]) -> BTreeSet<String> {
+        scopes.iter().map(ToString::to_string).collect()
+    }
+
+    fn authenticator(required: &[&str]) -> ScopeCheckAuthenticator<(), ()> {
+        ScopeCheckAuthenticator::new((), scopes(required))
+    }
+
+    fn authorized(scopes: Scopes) -> Option<Authorization> {
+        Some(Authorization {
+            subject: "subject".to_string(),
+            scopes,
+            issuer: None,
+        })
+    }
+
+    #[test]
+    fn rejects_missing_authorization() {
+        assert!(!authenticator(&["read"]).is_authorized(&None));
+    }
+
+    #[test]
+    fn allows_scopes_all_regardless_of_required_scopes() {
+        assert!(authenticator(&["read", "write"]).is_authorized(&authorized(Scopes::All)));
+    }
+
+    #[test]
+    fn allows_when_granted_scopes_are_a_superset() {
+        let granted = authorized(Scopes::Some(scopes(&["read", "write"])));
+        assert!(authenticator(&["read"]).is_authorized(&granted));
+    }
+
+    #[test]
+    fn rejects_when_a_required_scope_is_missing() {
+        let granted = authorized(Scopes::Some(scopes(&["read"])));
+        assert!(!authenticator(&["read", "write"]).is_authorized(&granted));
+    }
+
+    #[test]
+    fn empty_required_scopes_are_always_satisfied_by_some() {
+        let granted = authorized(Scopes::Some(scopes(&[])));
+        assert!(authenticator(&[]).is_authorized(&granted));
+    }
+}
@@ -2,7 +2,7 @@
 //! to a wrapped service.
 
 use crate::ContextualPayload;
-use hyper::{Body, Request};
+use hyper::Request;
 use std::marker::PhantomData;
 use std::task::{Context, Poll};
 
@@ -39,8 +39,8 @@ impl DropContextMakeService {
     }
 }
 
-impl<T, C> hyper::service::Service<T> for DropContextMakeService {
-    type Response = DropContextService<T, C>;
+impl<T, C, B> hyper::service::Service<T> for DropContextMakeService {
+    type Response = DropContextService<T, C, B>;
     type Error = std::io::Error;
     type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
 
@@ -56,13 +56,16 @@ impl<T, C> hyper::service::Service<T> for DropContextMakeService {
 /// Swagger Middleware that wraps a `hyper::service::Service`, and drops any contextual information
 /// on the request. Services will normally want to use `DropContextMakeService`, which will create
 /// a `DropContextService` to handle each connection.
+///
+/// Generic over the request body type `B` (defaulting to `hyper::Body` for
+/// source compatibility), matching the body type parameter on `AddContextService`.
 #[derive(Debug)]
-pub struct DropContextService<T, C> {
+pub struct DropContextService<T, C, B = hyper::Body> {
     inner: T,
-    marker: PhantomData<C>,
+    marker: PhantomData<(C, B)>,
 }
 
-impl<T, C> DropContextService<T, C> {
+impl<T, C, B> DropContextService<T, C, B> {
     /// Create a new AddContextService struct wrapping a value
     pub fn new(inner: T) -> Self {
         DropContextService {
@@ -72,10 +75,11 @@ impl<T, C> DropContextService<T, C> {
     }
 }
 
-impl<T, C> hyper::service::Service<ContextualPayload<C>> for DropContextService<T, C>
+impl<T, C, B> hyper::service::Service<ContextualPayload<C, B>> for DropContextService<T, C, B>
     where
+        B: http_body::Body + Send + 'static,
         C: Send + Sync + 'static,
-        T: hyper::service::Service<Request<Body>>,
+        T: hyper::service::Service<Request<B>>,
 {
     type Response = T::Response;
     type Error = T::Error;
@@ -85,7 +89,7 @@ impl<T, C> hyper::service::Service<ContextualPayload<C>> for DropContextService<
         Ok(()).into()
     }
 
-    fn call(&mut self, req: ContextualPayload<C>) -> Self::Future {
+    fn call(&mut self, req: ContextualPayload<C, B>) -> Self::Future {
         self.inner.call(req.inner)
     }
 }
\ No newline at end of file
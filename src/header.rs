@@ -114,9 +114,79 @@ ihv_generate!(u32);
 ihv_generate!(usize);
 ihv_generate!(isize);
 ihv_generate!(i32);
+ihv_generate!(f32);
+ihv_generate!(f64);
+
+// Derive Vec<T> for each numeric type in hyper::header::HeaderValue, using the
+// same comma-separated, trimmed, empty-skipping split/join logic as Vec<String>.
+
+macro_rules! ihv_vec_generate {
+    ($t:ident) => {
+        impl TryFrom<HeaderValue> for IntoHeaderValue<Vec<$t>> {
+            type Error = headers::Error;
+            fn try_from(hdr_value: HeaderValue) -> Result<Self, Self::Error> {
+                Ok(IntoHeaderValue(
+                    hdr_value
+                        .to_str()
+                        .map_err(|_| headers::Error::invalid())?
+                        .split(',')
+                        .filter_map(|x| match x.trim() {
+                            "" => None,
+                            y => Some(y.parse::<$t>()),
+                        })
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| headers::Error::invalid())?,
+                ))
+            }
+        }
+
+        impl Into<HeaderValue> for IntoHeaderValue<Vec<$t>> {
+            fn into(self) -> HeaderValue {
+                HeaderValue::from_str(
+                    &self
+                        .0
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+                .unwrap()
+            }
+        }
+    };
+}
+
+ihv_vec_generate!(u64);
+ihv_vec_generate!(i64);
+ihv_vec_generate!(i16);
+ihv_vec_generate!(u16);
+ihv_vec_generate!(u32);
+ihv_vec_generate!(usize);
+ihv_vec_generate!(isize);
+ihv_vec_generate!(i32);
+ihv_vec_generate!(f32);
+ihv_vec_generate!(f64);
 
 // Custom derivations
 
+impl TryFrom<HeaderValue> for IntoHeaderValue<bool> {
+    type Error = headers::Error;
+    fn try_from(hdr_value: HeaderValue) -> Result<Self, Self::Error> {
+        let value = hdr_value.to_str().map_err(|_| headers::Error::invalid())?;
+        match value {
+            "true" => Ok(IntoHeaderValue(true)),
+            "false" => Ok(IntoHeaderValue(false)),
+            _ => Err(headers::Error::invalid()),
+        }
+    }
+}
+
+impl Into<HeaderValue> for IntoHeaderValue<bool> {
+    fn into(self) -> HeaderValue {
+        HeaderValue::from_str(if self.0 { "true" } else { "false" }).unwrap()
+    }
+}
+
 impl TryFrom<HeaderValue> for IntoHeaderValue<Vec<String>> {
     type Error = headers::Error;
     fn try_from(hdr_value: HeaderValue) -> Result<Self, Self::Error> {
@@ -174,3 +244,44 @@ impl Into<HeaderValue> for IntoHeaderValue<DateTime<Utc>> {
         HeaderValue::from_str(self.0.to_rfc3339().as_str()).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bool_round_trips() {
+        for value in [true, false] {
+            let header: HeaderValue = IntoHeaderValue(value).into();
+            let parsed = IntoHeaderValue::<bool>::try_from(header).unwrap();
+            assert_eq!(*parsed, value);
+        }
+    }
+
+    #[test]
+    fn bool_rejects_malformed_value() {
+        let header = HeaderValue::from_static("yes");
+        assert!(IntoHeaderValue::<bool>::try_from(header).is_err());
+    }
+
+    #[test]
+    fn vec_f64_round_trips() {
+        let value = vec![1.5, -2.0, 3.25];
+        let header: HeaderValue = IntoHeaderValue(value.clone()).into();
+        let parsed = IntoHeaderValue::<Vec<f64>>::try_from(header).unwrap();
+        assert_eq!(*parsed, value);
+    }
+
+    #[test]
+    fn vec_f64_skips_blank_entries() {
+        let header = HeaderValue::from_static("1.5, , 2.5,");
+        let parsed = IntoHeaderValue::<Vec<f64>>::try_from(header).unwrap();
+        assert_eq!(*parsed, vec![1.5, 2.5]);
+    }
+
+    #[test]
+    fn vec_f64_rejects_malformed_entry() {
+        let header = HeaderValue::from_static("1.5, not-a-number");
+        assert!(IntoHeaderValue::<Vec<f64>>::try_from(header).is_err());
+    }
+}